@@ -0,0 +1,115 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use anyhow::Result;
+use axum::{
+    extract::{Path, State},
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    routing::get,
+    Json, Router,
+};
+use log::info;
+use serde::Serialize;
+use tokio::{net::TcpListener, sync::RwLock};
+
+use crate::{
+    codec::ScrapeData,
+    policy::Policy,
+    stats::Stats,
+    swarm::SwarmDb,
+};
+
+/// State shared between the UDP tracker and the HTTP management API.
+#[derive(Clone)]
+pub struct ApiState {
+    pub swarms: Arc<tokio::sync::Mutex<SwarmDb>>,
+    pub policy: Arc<RwLock<Policy>>,
+    pub stats: Arc<Stats>,
+    /// Bearer token required for the mutating `/torrent/{info_hash}/block` endpoints.
+    /// When `None`, those endpoints are open to anyone who can reach the API address.
+    pub api_token: Option<Arc<str>>,
+}
+
+#[derive(Serialize)]
+struct StatsResponse {
+    torrents: usize,
+    peers: usize,
+    seeders: usize,
+    leechers: usize,
+    packets_per_second: f64,
+}
+
+async fn get_stats(State(state): State<ApiState>) -> Json<StatsResponse> {
+    let totals = state.swarms.lock().await.totals();
+    Json(StatsResponse {
+        torrents: totals.torrents,
+        peers: totals.peers,
+        seeders: totals.seeders,
+        leechers: totals.leechers,
+        packets_per_second: state.stats.packets_per_second(),
+    })
+}
+
+async fn get_torrent(
+    State(state): State<ApiState>,
+    Path(info_hash): Path<String>,
+) -> Result<Json<ScrapeData>, StatusCode> {
+    let info_hash = info_hash.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+    state.swarms.lock().await.get(&info_hash).map(|swarm| Json(swarm.scrape_data())).ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Rejects the request unless it carries `Authorization: Bearer <api_token>`.
+/// A no-op when the tracker wasn't started with an API token configured.
+fn authorize(state: &ApiState, headers: &HeaderMap) -> Result<(), StatusCode> {
+    let Some(expected) = &state.api_token else {
+        return Ok(());
+    };
+
+    let provided = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    if provided == Some(expected.as_ref()) {
+        Ok(())
+    } else {
+        Err(StatusCode::UNAUTHORIZED)
+    }
+}
+
+async fn block_torrent(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    Path(info_hash): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    authorize(&state, &headers)?;
+    let info_hash = info_hash.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+    state.policy.write().await.block(info_hash);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn unblock_torrent(
+    State(state): State<ApiState>,
+    headers: HeaderMap,
+    Path(info_hash): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    authorize(&state, &headers)?;
+    let info_hash = info_hash.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+    state.policy.write().await.unblock(&info_hash);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn router(state: ApiState) -> Router {
+    Router::new()
+        .route("/stats", get(get_stats))
+        .route("/torrent/:info_hash", get(get_torrent))
+        .route("/torrent/:info_hash/block", axum::routing::post(block_torrent).delete(unblock_torrent))
+        .with_state(state)
+}
+
+/// Runs the HTTP management API until the process is terminated.
+pub async fn serve(addr: SocketAddr, state: ApiState) -> Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    info!("API listening on: {}", listener.local_addr()?);
+    axum::serve(listener, router(state)).await?;
+    Ok(())
+}
@@ -0,0 +1,130 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::RwLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const SECRET_LEN: usize = 32;
+/// Connection IDs are bucketed into windows of this many seconds, per BEP-15's
+/// recommendation that they remain valid for roughly two minutes.
+const BUCKET_SECONDS: u64 = 60;
+
+/// Issues and validates BEP-15 connection IDs without keeping any per-client
+/// state: a connection ID is an HMAC of the client's address and the current
+/// time bucket, signed with a secret only the tracker knows. Validating one
+/// just means recomputing the HMAC for the current and preceding buckets and
+/// comparing. The secret is generated randomly per instance and is never
+/// shared, so an ID is only accepted by the instance that issued it.
+pub struct ConnectionAuth {
+    secret: RwLock<[u8; SECRET_LEN]>,
+    validity_window: u64,
+}
+
+impl ConnectionAuth {
+    /// `validity_window` is the number of one-minute buckets (including the
+    /// current one) a connection ID is accepted in, e.g. `2` tolerates the
+    /// ~2-minute window clients may straddle around a bucket boundary.
+    pub fn new(validity_window: u64) -> Self {
+        Self { secret: RwLock::new(Self::random_secret()), validity_window: validity_window.max(1) }
+    }
+
+    fn random_secret() -> [u8; SECRET_LEN] {
+        let mut secret = [0u8; SECRET_LEN];
+        rand::thread_rng().fill_bytes(&mut secret);
+        secret
+    }
+
+    /// Replaces the signing secret, invalidating every connection ID issued so far.
+    pub fn rotate(&self) {
+        *self.secret.write().unwrap() = Self::random_secret();
+    }
+
+    fn current_bucket() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() / BUCKET_SECONDS
+    }
+
+    fn addr_bytes(addr: SocketAddr) -> [u8; 18] {
+        let mut bytes = [0u8; 18];
+        let ip_bytes: [u8; 16] = match addr.ip() {
+            IpAddr::V4(ip) => ip.to_ipv6_mapped().octets(),
+            IpAddr::V6(ip) => ip.octets(),
+        };
+        bytes[..16].copy_from_slice(&ip_bytes);
+        bytes[16..].copy_from_slice(&addr.port().to_be_bytes());
+        bytes
+    }
+
+    fn sign(&self, addr: SocketAddr, bucket: u64) -> u64 {
+        let secret = self.secret.read().unwrap();
+        let mut mac =
+            HmacSha256::new_from_slice(secret.as_slice()).expect("HMAC accepts keys of any length");
+        mac.update(&Self::addr_bytes(addr));
+        mac.update(&bucket.to_be_bytes());
+        let tag = mac.finalize().into_bytes();
+        u64::from_be_bytes(tag[..8].try_into().expect("HMAC-SHA256 output is at least 8 bytes"))
+    }
+
+    /// Issues a connection ID for `addr`, valid starting now.
+    pub fn issue(&self, addr: SocketAddr) -> u64 {
+        self.sign(addr, Self::current_bucket())
+    }
+
+    /// Checks whether `connection_id` was issued to `addr` within the validity window.
+    pub fn validate(&self, addr: SocketAddr, connection_id: u64) -> bool {
+        let current = Self::current_bucket();
+        (0..self.validity_window).any(|age| self.sign(addr, current.saturating_sub(age)) == connection_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(port: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], port))
+    }
+
+    #[tokio::test]
+    async fn test_validate_accepts_freshly_issued_id() {
+        let auth = ConnectionAuth::new(2);
+        let addr = client(1234);
+        let id = auth.issue(addr);
+
+        assert!(auth.validate(addr, id));
+    }
+
+    #[tokio::test]
+    async fn test_validate_rejects_wrong_address() {
+        let auth = ConnectionAuth::new(2);
+        let id = auth.issue(client(1234));
+
+        assert!(!auth.validate(client(5678), id));
+    }
+
+    #[tokio::test]
+    async fn test_validate_rejects_id_older_than_validity_window() {
+        let auth = ConnectionAuth::new(2);
+        let addr = client(1234);
+        let stale_bucket = ConnectionAuth::current_bucket().saturating_sub(auth.validity_window);
+        let stale_id = auth.sign(addr, stale_bucket);
+
+        assert!(!auth.validate(addr, stale_id));
+    }
+
+    #[tokio::test]
+    async fn test_validate_rejects_everything_after_rotate() {
+        let auth = ConnectionAuth::new(2);
+        let addr = client(1234);
+        let id = auth.issue(addr);
+
+        auth.rotate();
+
+        assert!(!auth.validate(addr, id));
+    }
+}
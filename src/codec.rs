@@ -1,7 +1,8 @@
 use std::{
     fmt::{Display, Formatter},
-    io::Cursor,
+    io::{Cursor, Write},
     net::Ipv4Addr,
+    str::FromStr,
 };
 
 use anyhow::anyhow;
@@ -20,6 +21,7 @@ pub enum TrackerPacket {
     AnnounceResponse(AnnounceResponse),
     ScrapeRequest(ScrapeRequest),
     ScrapeResponse(ScrapeResponse),
+    ErrorResponse(ErrorResponse),
 }
 
 impl Encoder<TrackerPacket> for TrackerCodec {
@@ -51,6 +53,9 @@ impl Encoder<TrackerPacket> for TrackerCodec {
             TrackerPacket::ScrapeResponse(res) => {
                 cursor.write_be(&res).map_err(|e| anyhow!(e))?;
             }
+            TrackerPacket::ErrorResponse(res) => {
+                cursor.write_be(&res).map_err(|e| anyhow!(e))?;
+            }
         }
 
         cursor.set_position(0);
@@ -110,6 +115,9 @@ impl Decoder for TrackerCodec {
                 Ok(Action::Scrape) => {
                     return Ok(Some(TrackerPacket::ScrapeResponse(reader.read_be()?)))
                 }
+                Ok(Action::Error) => {
+                    return Ok(Some(TrackerPacket::ErrorResponse(reader.read_be()?)))
+                }
                 _ => {}
             }
 
@@ -126,6 +134,7 @@ pub enum Action {
     Connect = 0,
     Announce = 1,
     Scrape = 2,
+    Error = 3,
 }
 
 impl TryFrom<u32> for Action {
@@ -136,6 +145,7 @@ impl TryFrom<u32> for Action {
             0 => Ok(Action::Connect),
             1 => Ok(Action::Announce),
             2 => Ok(Action::Scrape),
+            3 => Ok(Action::Error),
             _ => Err(anyhow!("Unknown action: {}", value)),
         }
     }
@@ -179,37 +189,63 @@ pub struct ConnectResponse {
     pub connection_id: u64,
 }
 
+/// An announce's `event` field. Unlike `Action`, an unrecognized value here
+/// must not fail decoding outright: the rest of the packet (including the
+/// `transaction_id` we need to reply with) is still well-formed, so we carry
+/// the raw value through and let `handle_packet` report it as a proper
+/// BEP-15 error instead of dropping the packet.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
-#[binrw]
-#[brw(big, repr = u32)]
-#[repr(u32)]
 pub enum AnnounceEvent {
-    None = 0,
-    Completed = 1,
-    Started = 2,
-    Stopped = 3,
+    None,
+    Completed,
+    Started,
+    Stopped,
+    Unsupported(u32),
 }
 
-impl TryFrom<u32> for AnnounceEvent {
-    type Error = anyhow::Error;
+impl AnnounceEvent {
+    pub fn is_supported(&self) -> bool {
+        !matches!(self, AnnounceEvent::Unsupported(_))
+    }
+}
 
-    fn try_from(value: u32) -> Result<Self, Self::Error> {
+impl From<u32> for AnnounceEvent {
+    fn from(value: u32) -> Self {
         match value {
-            0 => Ok(AnnounceEvent::None),
-            1 => Ok(AnnounceEvent::Completed),
-            2 => Ok(AnnounceEvent::Started),
-            3 => Ok(AnnounceEvent::Stopped),
-            _ => Err(anyhow!("Unsupported announce event: {}!", value)),
+            0 => AnnounceEvent::None,
+            1 => AnnounceEvent::Completed,
+            2 => AnnounceEvent::Started,
+            3 => AnnounceEvent::Stopped,
+            other => AnnounceEvent::Unsupported(other),
         }
     }
 }
 
 impl From<AnnounceEvent> for u32 {
     fn from(value: AnnounceEvent) -> Self {
-        value as u32
+        match value {
+            AnnounceEvent::None => 0,
+            AnnounceEvent::Completed => 1,
+            AnnounceEvent::Started => 2,
+            AnnounceEvent::Stopped => 3,
+            AnnounceEvent::Unsupported(raw) => raw,
+        }
     }
 }
 
+#[binrw::parser(reader, endian)]
+fn announce_event_parser() -> BinResult<AnnounceEvent> {
+    let raw: u32 = <_>::read_options(reader, endian, ())?;
+    Ok(AnnounceEvent::from(raw))
+}
+
+#[binrw::writer(writer, endian)]
+fn announce_event_writer(event: &AnnounceEvent) -> BinResult<()> {
+    let raw: u32 = (*event).into();
+    raw.write_options(writer, endian, ())?;
+    Ok(())
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 #[binrw]
 pub struct PeerId {
@@ -254,6 +290,8 @@ pub struct AnnounceRequest {
     pub downloaded: u64,
     pub left: u64,
     pub uploaded: u64,
+    #[br(parse_with = announce_event_parser)]
+    #[bw(write_with = announce_event_writer)]
     pub event: AnnounceEvent,
     #[br(parse_with = ip_addr_parser)]
     #[bw(write_with = ip_addr_writer)]
@@ -325,6 +363,23 @@ pub struct ScrapeResponse {
     pub data: Vec<ScrapeData>,
 }
 
+// Offset  Size            Name            Value
+// 0       32-bit integer  action          3 // error
+// 4       32-bit integer  transaction_id
+// 8       ASCII string    message
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[binrw]
+#[brw(big)]
+#[repr(C)]
+pub struct ErrorResponse {
+    pub action: Action,
+    pub transaction_id: u32,
+    #[br(parse_with = error_message_parser)]
+    #[bw(write_with = error_message_writer)]
+    pub message: String,
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 #[binrw]
 #[brw(big)]
@@ -350,6 +405,23 @@ impl Display for InfoHash {
     }
 }
 
+impl FromStr for InfoHash {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 40 {
+            return Err(anyhow!("expected a 40-character hex info hash, got {:?}", s));
+        }
+
+        let mut hash = [0u8; 20];
+        for (i, byte) in hash.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&s[i * 2..i * 2 + 2], 16).map_err(|e| anyhow!(e))?;
+        }
+
+        Ok(InfoHash { hash })
+    }
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
 #[binrw]
 #[brw(big)]
@@ -373,6 +445,17 @@ fn ip_addr_writer(ip: &Ipv4Addr) -> BinResult<()> {
     Ok(())
 }
 
+#[binrw::parser(reader, endian)]
+fn error_message_parser() -> BinResult<String> {
+    let bytes: Vec<u8> = until_eof(reader, endian, ())?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[binrw::writer(writer, endian)]
+fn error_message_writer(message: &String) -> BinResult<()> {
+    writer.write_all(message.as_bytes()).map_err(binrw::Error::Io)
+}
+
 #[cfg(test)]
 mod tests {
     use std::io::Cursor;
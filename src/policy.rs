@@ -0,0 +1,78 @@
+use std::{collections::HashSet, fs, path::Path};
+
+use anyhow::Result;
+use clap::ValueEnum;
+
+use crate::codec::InfoHash;
+
+/// Controls which torrents this tracker is willing to track.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum TrackerMode {
+    /// Any announced torrent is tracked, no allowlist required.
+    Dynamic,
+    /// Only torrents present in the allowlist are tracked; unknown hashes are rejected.
+    Static,
+    /// Same as `Static`, but intended for trackers that are never meant to serve the public internet.
+    Private,
+}
+
+impl Default for TrackerMode {
+    fn default() -> Self {
+        TrackerMode::Dynamic
+    }
+}
+
+/// Decides whether a given `InfoHash` may be announced or scraped, following
+/// the configured [`TrackerMode`] plus an optional blocklist/allowlist.
+#[derive(Debug, Default)]
+pub struct Policy {
+    pub mode: TrackerMode,
+    blocklist: HashSet<InfoHash>,
+    allowlist: HashSet<InfoHash>,
+}
+
+impl Policy {
+    pub fn new(mode: TrackerMode, blocklist: HashSet<InfoHash>, allowlist: HashSet<InfoHash>) -> Self {
+        Self { mode, blocklist, allowlist }
+    }
+
+    /// Returns `Err` with a human-readable reason if `hash` may not be tracked.
+    pub fn check(&self, hash: &InfoHash) -> Result<(), &'static str> {
+        if self.blocklist.contains(hash) {
+            return Err("This torrent has been blocked by this tracker");
+        }
+
+        match self.mode {
+            TrackerMode::Dynamic => Ok(()),
+            TrackerMode::Static | TrackerMode::Private => {
+                if self.allowlist.contains(hash) {
+                    Ok(())
+                } else {
+                    Err("This torrent is not tracked by this server")
+                }
+            }
+        }
+    }
+
+    /// Adds `hash` to the blocklist at runtime, e.g. via the management API.
+    pub fn block(&mut self, hash: InfoHash) {
+        self.blocklist.insert(hash);
+    }
+
+    /// Removes `hash` from the blocklist at runtime.
+    pub fn unblock(&mut self, hash: &InfoHash) {
+        self.blocklist.remove(hash);
+    }
+}
+
+/// Loads a newline-separated list of hex-encoded info hashes, e.g. one produced by `btshowmetainfo`.
+/// Blank lines and lines starting with `#` are ignored.
+pub fn load_hash_list(path: &Path) -> Result<HashSet<InfoHash>> {
+    let contents = fs::read_to_string(path)?;
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.parse())
+        .collect()
+}
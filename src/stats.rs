@@ -0,0 +1,37 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Instant,
+};
+
+/// Tracks how many UDP packets the tracker has processed, for the `/stats` API.
+#[derive(Debug)]
+pub struct Stats {
+    packets: AtomicU64,
+    started_at: Instant,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self { packets: AtomicU64::new(0), started_at: Instant::now() }
+    }
+
+    pub fn record_packet(&self) {
+        self.packets.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Average packets processed per second since the tracker started.
+    pub fn packets_per_second(&self) -> f64 {
+        let elapsed = self.started_at.elapsed().as_secs_f64();
+        if elapsed == 0.0 {
+            0.0
+        } else {
+            self.packets.load(Ordering::Relaxed) as f64 / elapsed
+        }
+    }
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,228 @@
+use std::{
+    collections::HashMap,
+    net::Ipv4Addr,
+    time::{Duration, Instant},
+};
+
+use rand::Rng;
+
+use crate::codec::{InfoHash, Peer, PeerId, ScrapeData};
+
+/// A single peer that is currently announcing for a torrent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerEntry {
+    pub ip_address: Ipv4Addr,
+    pub port: u16,
+    pub left: u64,
+    pub last_seen: Instant,
+}
+
+impl PeerEntry {
+    /// A peer that reports `left == 0` already has the full torrent, i.e. it's seeding.
+    pub fn is_seeder(&self) -> bool {
+        self.left == 0
+    }
+}
+
+impl From<&PeerEntry> for Peer {
+    fn from(entry: &PeerEntry) -> Self {
+        Peer { ip_address: entry.ip_address, port: entry.port }
+    }
+}
+
+/// The live state of a single torrent's swarm: who's in it, and how many
+/// peers have ever finished downloading it.
+#[derive(Debug, Clone, Default)]
+pub struct SwarmEntry {
+    pub peers: HashMap<PeerId, PeerEntry>,
+    pub completed: u32,
+}
+
+impl SwarmEntry {
+    pub fn seeders(&self) -> u32 {
+        self.peers.values().filter(|peer| peer.is_seeder()).count() as u32
+    }
+
+    pub fn leechers(&self) -> u32 {
+        self.peers.values().filter(|peer| !peer.is_seeder()).count() as u32
+    }
+
+    pub fn scrape_data(&self) -> ScrapeData {
+        ScrapeData { seeders: self.seeders(), completed: self.completed, leechers: self.leechers() }
+    }
+
+    /// Returns a uniformly random sample of up to `count` peers, excluding `exclude`
+    /// (the peer that's announcing). Uses reservoir sampling so a swarm much larger
+    /// than `count` never has to be materialized into a `Vec` first.
+    pub fn sample_peers(&self, count: usize, exclude: &PeerId) -> Vec<Peer> {
+        let mut reservoir = Vec::with_capacity(count);
+        let mut rng = rand::thread_rng();
+        let mut seen = 0usize;
+
+        for (peer_id, peer) in &self.peers {
+            if peer_id == exclude {
+                continue;
+            }
+
+            if seen < count {
+                reservoir.push(Peer::from(peer));
+            } else {
+                let slot = rng.gen_range(0..=seen);
+                if slot < count {
+                    reservoir[slot] = Peer::from(peer);
+                }
+            }
+
+            seen += 1;
+        }
+
+        reservoir
+    }
+}
+
+/// In-memory database of every swarm the tracker currently knows about,
+/// keyed by the torrent's `InfoHash`.
+#[derive(Debug, Default)]
+pub struct SwarmDb {
+    swarms: HashMap<InfoHash, SwarmEntry>,
+}
+
+impl SwarmDb {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the swarm for `info_hash`, creating an empty one if it doesn't exist yet.
+    pub fn entry(&mut self, info_hash: InfoHash) -> &mut SwarmEntry {
+        self.swarms.entry(info_hash).or_default()
+    }
+
+    pub fn get(&self, info_hash: &InfoHash) -> Option<&SwarmEntry> {
+        self.swarms.get(info_hash)
+    }
+
+    /// Aggregate counts across every swarm, for the `/stats` API.
+    pub fn totals(&self) -> SwarmTotals {
+        self.swarms.values().fold(
+            SwarmTotals { torrents: self.swarms.len(), ..SwarmTotals::default() },
+            |mut totals, swarm| {
+                totals.peers += swarm.peers.len();
+                totals.seeders += swarm.seeders() as usize;
+                totals.leechers += swarm.leechers() as usize;
+                totals
+            },
+        )
+    }
+
+    /// Evicts peers that haven't re-announced within `max_age`. When `drop_empty`
+    /// is set, torrents whose swarm becomes empty as a result are removed too.
+    /// Returns `(peers_evicted, torrents_evicted)`, for logging.
+    pub fn housekeep(&mut self, max_age: Duration, drop_empty: bool) -> (usize, usize) {
+        let torrents_before = self.swarms.len();
+        let mut peers_evicted = 0;
+
+        self.swarms.retain(|_, swarm| {
+            let peers_before = swarm.peers.len();
+            swarm.peers.retain(|_, peer| peer.last_seen.elapsed() < max_age);
+            peers_evicted += peers_before - swarm.peers.len();
+
+            !drop_empty || !swarm.peers.is_empty()
+        });
+
+        (peers_evicted, torrents_before - self.swarms.len())
+    }
+}
+
+/// Aggregate swarm counters returned by [`SwarmDb::totals`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SwarmTotals {
+    pub torrents: usize,
+    pub peers: usize,
+    pub seeders: usize,
+    pub leechers: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer_id(byte: u8) -> PeerId {
+        PeerId { inner: [byte; 20] }
+    }
+
+    fn peer_entry(port: u16) -> PeerEntry {
+        PeerEntry { ip_address: Ipv4Addr::new(127, 0, 0, 1), port, left: 0, last_seen: Instant::now() }
+    }
+
+    fn info_hash(byte: u8) -> InfoHash {
+        InfoHash { hash: [byte; 20] }
+    }
+
+    #[test]
+    fn test_sample_peers_excludes_announcing_peer() {
+        let mut swarm = SwarmEntry::default();
+        let excluded = peer_id(0);
+        swarm.peers.insert(excluded, peer_entry(1));
+        for i in 1..5u8 {
+            swarm.peers.insert(peer_id(i), peer_entry(1000 + i as u16));
+        }
+
+        for _ in 0..20 {
+            let sample = swarm.sample_peers(10, &excluded);
+            assert!(!sample.contains(&Peer::from(&peer_entry(1))));
+        }
+    }
+
+    #[test]
+    fn test_sample_peers_caps_at_count() {
+        let mut swarm = SwarmEntry::default();
+        for i in 0..50u8 {
+            swarm.peers.insert(peer_id(i), peer_entry(i as u16 + 1));
+        }
+
+        let sample = swarm.sample_peers(10, &peer_id(255));
+
+        assert_eq!(sample.len(), 10);
+    }
+
+    #[test]
+    fn test_housekeep_evicts_only_stale_peers() {
+        let mut db = SwarmDb::new();
+        let hash = info_hash(1);
+        {
+            let swarm = db.entry(hash);
+            swarm.peers.insert(
+                peer_id(1),
+                PeerEntry { last_seen: Instant::now() - Duration::from_secs(120), ..peer_entry(1) },
+            );
+            swarm.peers.insert(peer_id(2), peer_entry(2));
+        }
+
+        let (peers_evicted, torrents_evicted) = db.housekeep(Duration::from_secs(60), false);
+
+        assert_eq!(peers_evicted, 1);
+        assert_eq!(torrents_evicted, 0);
+        assert_eq!(db.get(&hash).unwrap().peers.len(), 1);
+    }
+
+    #[test]
+    fn test_housekeep_drops_empty_swarms_only_when_requested() {
+        let mut db = SwarmDb::new();
+        let hash = info_hash(2);
+        {
+            let swarm = db.entry(hash);
+            swarm.peers.insert(
+                peer_id(1),
+                PeerEntry { last_seen: Instant::now() - Duration::from_secs(120), ..peer_entry(1) },
+            );
+        }
+
+        let (_, torrents_evicted) = db.housekeep(Duration::from_secs(60), false);
+        assert_eq!(torrents_evicted, 0);
+        assert!(db.get(&hash).is_some());
+
+        let (_, torrents_evicted) = db.housekeep(Duration::from_secs(60), true);
+        assert_eq!(torrents_evicted, 1);
+        assert!(db.get(&hash).is_none());
+    }
+}
@@ -1,28 +1,52 @@
 #![warn(rust_2018_idioms)]
 
+use std::net::SocketAddr;
 use std::{
     net::{AddrParseError, Ipv4Addr, SocketAddrV4},
+    path::PathBuf,
     str::FromStr,
+    sync::Arc,
 };
-use std::net::SocketAddr;
 
 use anyhow::Result;
 
 use log::{debug, info, warn};
-use tokio::{net::UdpSocket, signal};
+use tokio::{
+    net::UdpSocket,
+    signal,
+    sync::{Mutex, RwLock},
+};
 use tokio_stream::StreamExt;
 use tokio_util::udp::UdpFramed;
 
+mod api;
+mod auth;
 mod codec;
+mod policy;
+mod stats;
+mod swarm;
 
 use clap::Parser;
 use futures::SinkExt;
 
-use crate::codec::{
-    Action, AnnounceResponse, ConnectResponse, Peer, ScrapeData, ScrapeResponse, TrackerCodec,
-    TrackerPacket,
+use crate::{
+    api::ApiState,
+    auth::ConnectionAuth,
+    codec::{
+        Action, AnnounceEvent, AnnounceResponse, ConnectResponse, ErrorResponse, Peer,
+        ScrapeResponse, TrackerCodec, TrackerPacket,
+    },
+    policy::{load_hash_list, Policy, TrackerMode},
+    stats::Stats,
+    swarm::{PeerEntry, SwarmDb},
 };
 
+/// Default number of peers handed back when a client doesn't specify `peers_wanted`.
+const DEFAULT_PEERS_WANTED: usize = 50;
+/// Upper bound on the number of peers returned in a single announce response,
+/// regardless of what the client asks for.
+const MAX_PEERS_WANTED: usize = 200;
+
 #[derive(Parser, Debug)]
 #[clap(version = "1.0", author = "not-jan")]
 /// Structure representing command line options for the program.
@@ -52,22 +76,124 @@ pub struct Opts {
         default_value = "600"
     )]
     pub interval: u32,
+
+    /// How often, in seconds, the connection-ID signing secret is rotated.
+    /// Rotating the secret invalidates every connection ID issued so far.
+    #[clap(
+        env = "SECRET_ROTATION_INTERVAL",
+        long = "secret-rotation-interval",
+        default_value = "3600"
+    )]
+    pub secret_rotation_interval: u64,
+
+    /// Number of one-minute buckets a connection ID remains valid for.
+    #[clap(
+        env = "CONNECTION_ID_VALIDITY_WINDOW",
+        long = "connection-id-validity-window",
+        default_value = "2"
+    )]
+    pub connection_id_validity_window: u64,
+
+    /// Which torrents this tracker is willing to track.
+    #[clap(
+        value_enum,
+        env = "TRACKER_MODE",
+        long = "mode",
+        default_value = "dynamic"
+    )]
+    pub mode: TrackerMode,
+
+    /// Path to a newline-separated file of hex-encoded info hashes that are never tracked.
+    #[clap(env = "BLOCKLIST", long = "blocklist")]
+    pub blocklist: Option<PathBuf>,
+
+    /// Path to a newline-separated file of hex-encoded info hashes this tracker serves
+    /// in `static` or `private` mode.
+    #[clap(env = "ALLOWLIST", long = "allowlist")]
+    pub allowlist: Option<PathBuf>,
+
+    /// A peer is evicted once it hasn't re-announced for this many multiples of `interval`.
+    #[clap(
+        env = "PEER_EXPIRY_MULTIPLIER",
+        long = "peer-expiry-multiplier",
+        default_value = "2"
+    )]
+    pub peer_expiry_multiplier: u32,
+
+    /// The IPv4 address the HTTP management API should listen on. The API is disabled
+    /// unless this is set.
+    #[clap(env = "API_LISTEN_ADDRESS", long = "api-listen-address")]
+    pub api_listen_address: Option<String>,
+
+    /// The port the HTTP management API should bind to.
+    #[clap(env = "API_LISTEN_PORT", long = "api-listen-port", default_value = "8081")]
+    pub api_listen_port: u16,
+
+    /// Bearer token required to call the mutating `/torrent/{info_hash}/block` endpoints.
+    /// If unset, those endpoints are unauthenticated.
+    #[clap(env = "API_TOKEN", long = "api-token")]
+    pub api_token: Option<String>,
 }
 
 struct Tracker {
     frame: UdpFramed<TrackerCodec>,
     static_peers: Vec<Peer>,
+    swarms: Arc<Mutex<SwarmDb>>,
+    auth: ConnectionAuth,
+    policy: Arc<RwLock<Policy>>,
+    stats: Arc<Stats>,
     interval: u32,
+    secret_rotation_interval: u64,
+    peer_expiry_multiplier: u32,
 }
 
 impl Tracker {
-    pub fn new(socket: UdpSocket, static_peers: Vec<SocketAddrV4>, interval: u32) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        socket: UdpSocket,
+        static_peers: Vec<SocketAddrV4>,
+        interval: u32,
+        secret_rotation_interval: u64,
+        connection_id_validity_window: u64,
+        policy: Arc<RwLock<Policy>>,
+        peer_expiry_multiplier: u32,
+        swarms: Arc<Mutex<SwarmDb>>,
+        stats: Arc<Stats>,
+    ) -> Self {
         let frame = UdpFramed::new(socket, TrackerCodec {});
         let static_peers = static_peers
             .into_iter()
             .map(|addr| Peer { ip_address: *addr.ip(), port: addr.port() })
             .collect();
-        Tracker { frame, static_peers, interval }
+        Tracker {
+            frame,
+            static_peers,
+            swarms,
+            auth: ConnectionAuth::new(connection_id_validity_window),
+            policy,
+            stats,
+            interval,
+            secret_rotation_interval,
+            peer_expiry_multiplier,
+        }
+    }
+
+    /// Builds a BEP-15 error response echoing the failed request's transaction ID.
+    fn error(transaction_id: u32, message: impl Into<String>) -> TrackerPacket {
+        TrackerPacket::ErrorResponse(ErrorResponse { action: Action::Error, transaction_id, message: message.into() })
+    }
+
+    /// Picks the peer IP to store for an announce: clients are allowed to
+    /// report `0.0.0.0` to mean "use the address this packet arrived from".
+    fn peer_ip(request_ip: Ipv4Addr, addr: SocketAddr) -> Ipv4Addr {
+        if request_ip.is_unspecified() {
+            match addr.ip() {
+                std::net::IpAddr::V4(ip) => ip,
+                std::net::IpAddr::V6(_) => request_ip,
+            }
+        } else {
+            request_ip
+        }
     }
 
     async fn handle_packet(&self, packet: TrackerPacket, addr: SocketAddr) -> Result<Option<TrackerPacket>> {
@@ -77,33 +203,97 @@ impl Tracker {
                 Some(TrackerPacket::ConnectResponse(ConnectResponse {
                     action: Action::Connect,
                     transaction_id: request.transaction_id,
-                    connection_id: rand::random(),
+                    connection_id: self.auth.issue(addr),
                 }))
             }
             TrackerPacket::AnnounceRequest(request) => {
+                if !self.auth.validate(addr, request.connection_id) {
+                    debug!("[{addr}] Rejected announce with invalid connection ID");
+                    return Ok(Some(Self::error(request.transaction_id, "Invalid connection ID")));
+                }
+                if let Err(reason) = self.policy.read().await.check(&request.info_hash) {
+                    debug!("[{addr}] Rejected announce for {}: {reason}", request.info_hash);
+                    return Ok(Some(Self::error(request.transaction_id, reason)));
+                }
+                if !request.event.is_supported() {
+                    debug!("[{addr}] Rejected announce with unsupported event {:?}", request.event);
+                    return Ok(Some(Self::error(request.transaction_id, "Unsupported event")));
+                }
+                if (request.peers_wanted as i32) < -1 {
+                    debug!("[{addr}] Rejected announce with malformed num_want {}", request.peers_wanted as i32);
+                    return Ok(Some(Self::error(request.transaction_id, "Malformed num_want")));
+                }
                 debug!("[{addr}] Received announce request: {}", request.info_hash);
+
+                let ip_address = Self::peer_ip(request.ip_address, addr);
+                let port = request.port.unwrap_or(0);
+
+                let wanted = match request.peers_wanted {
+                    u32::MAX => DEFAULT_PEERS_WANTED,
+                    n => (n as usize).min(MAX_PEERS_WANTED),
+                };
+                let swarm_budget = wanted.saturating_sub(self.static_peers.len());
+
+                let (seeders, leechers, mut peers) = {
+                    let mut swarms = self.swarms.lock().await;
+                    let swarm = swarms.entry(request.info_hash);
+
+                    match request.event {
+                        AnnounceEvent::Stopped => {
+                            swarm.peers.remove(&request.peer_id);
+                        }
+                        event => {
+                            swarm.peers.insert(
+                                request.peer_id,
+                                PeerEntry {
+                                    ip_address,
+                                    port,
+                                    left: request.left,
+                                    last_seen: std::time::Instant::now(),
+                                },
+                            );
+                            if event == AnnounceEvent::Completed {
+                                swarm.completed += 1;
+                            }
+                        }
+                    }
+
+                    let sample = swarm.sample_peers(swarm_budget, &request.peer_id);
+                    (swarm.seeders(), swarm.leechers(), sample)
+                };
+
+                peers.extend(self.static_peers.iter().copied());
+                peers.truncate(wanted);
+
                 Some(TrackerPacket::AnnounceResponse(AnnounceResponse {
                     action: Action::Announce,
                     transaction_id: request.transaction_id,
                     interval: self.interval,
-                    leechers: 0,
-                    seeders: self.static_peers.len() as u32,
-                    peers: self.static_peers.clone(),
+                    leechers,
+                    seeders: seeders + self.static_peers.len() as u32,
+                    peers,
                 }))
             }
             TrackerPacket::ScrapeRequest(request) => {
+                if !self.auth.validate(addr, request.connection_id) {
+                    debug!("[{addr}] Rejected scrape with invalid connection ID");
+                    return Ok(Some(Self::error(request.transaction_id, "Invalid connection ID")));
+                }
+                let policy = self.policy.read().await;
+                if let Some(reason) = request.hashes.iter().find_map(|hash| policy.check(hash).err()) {
+                    debug!("[{addr}] Rejected scrape: {reason}");
+                    return Ok(Some(Self::error(request.transaction_id, reason)));
+                }
+                drop(policy);
                 debug!("[{addr}] Received scrape request for {} hashes", request.hashes.len());
+                let swarms = self.swarms.lock().await;
                 Some(TrackerPacket::ScrapeResponse(ScrapeResponse {
                     action: Action::Scrape,
                     transaction_id: request.transaction_id,
                     data: request
                         .hashes
-                        .into_iter()
-                        .map(|_| ScrapeData {
-                            seeders: self.static_peers.len() as u32,
-                            completed: self.static_peers.len() as u32,
-                            leechers: 0,
-                        })
+                        .iter()
+                        .map(|hash| swarms.get(hash).map(|swarm| swarm.scrape_data()).unwrap_or_default())
                         .collect::<Vec<_>>(),
                 }))
             }
@@ -112,27 +302,51 @@ impl Tracker {
     }
 
     pub async fn start(&mut self) -> Result<()> {
+        let mut secret_rotation =
+            tokio::time::interval(std::time::Duration::from_secs(self.secret_rotation_interval));
+        // The first tick fires immediately; we already seeded a fresh secret in `new`.
+        secret_rotation.tick().await;
+
+        let mut housekeeping = tokio::time::interval(std::time::Duration::from_secs(self.interval as u64));
+        housekeeping.tick().await;
+        let max_peer_age =
+            std::time::Duration::from_secs(self.interval as u64 * self.peer_expiry_multiplier as u64);
+
         loop {
             tokio::select! {
                 _ = signal::ctrl_c() => {
                     info!("Received Ctrl-C, shutting down");
                     break;
                 },
+                _ = secret_rotation.tick() => {
+                    debug!("Rotating connection-ID signing secret");
+                    self.auth.rotate();
+                },
+                _ = housekeeping.tick() => {
+                    let drop_empty = self.policy.read().await.mode == TrackerMode::Dynamic;
+                    let (peers, torrents) = self.swarms.lock().await.housekeep(max_peer_age, drop_empty);
+                    if peers > 0 || torrents > 0 {
+                        debug!("Housekeeping evicted {peers} stale peers and {torrents} empty torrents");
+                    }
+                },
                 result = self.frame.next() => match result {
                     Some(Err(e)) => {
                         debug!("Failed to parse request: {}", e);
                         continue;
                     },
                     None => break,
-                    Some(Ok((packet, addr))) => match self.handle_packet(packet, addr).await {
-                        Ok(Some(response)) => if let Err(e) = self.frame.send((response, addr)).await {
-                            warn!("[{}] Failed to send reply: {}", addr, e);
-                        },
-                        // Client sent a valid packet, but it doesn't warrant a reply.
-                        Ok(None) => {}
-                        // Client sent something invalid!
-                        Err(e) => {
-                            debug!("[{}] Received invalid packet: {}", addr, e);
+                    Some(Ok((packet, addr))) => {
+                        self.stats.record_packet();
+                        match self.handle_packet(packet, addr).await {
+                            Ok(Some(response)) => if let Err(e) = self.frame.send((response, addr)).await {
+                                warn!("[{}] Failed to send reply: {}", addr, e);
+                            },
+                            // Client sent a valid packet, but it doesn't warrant a reply.
+                            Ok(None) => {}
+                            // Client sent something invalid!
+                            Err(e) => {
+                                debug!("[{}] Received invalid packet: {}", addr, e);
+                            }
                         }
                     },
                 }
@@ -161,7 +375,48 @@ async fn main() -> Result<()> {
         .collect::<Result<Vec<SocketAddrV4>, AddrParseError>>()?;
 
     info!("Loaded {} static peers", static_peers.len());
-    let mut tracker = Tracker::new(socket, static_peers, args.interval);
+
+    let blocklist = args.blocklist.map(|path| load_hash_list(&path)).transpose()?.unwrap_or_default();
+    let allowlist = args.allowlist.map(|path| load_hash_list(&path)).transpose()?.unwrap_or_default();
+    info!(
+        "Running in {:?} mode with {} blocklisted and {} allowlisted hashes",
+        args.mode,
+        blocklist.len(),
+        allowlist.len()
+    );
+    let policy = Arc::new(RwLock::new(Policy::new(args.mode, blocklist, allowlist)));
+    let swarms = Arc::new(Mutex::new(SwarmDb::new()));
+    let stats = Arc::new(Stats::new());
+
+    if let Some(api_listen_address) = args.api_listen_address {
+        let api_addr = SocketAddr::V4(SocketAddrV4::new(
+            Ipv4Addr::from_str(&api_listen_address)?,
+            args.api_listen_port,
+        ));
+        let api_state = ApiState {
+            swarms: swarms.clone(),
+            policy: policy.clone(),
+            stats: stats.clone(),
+            api_token: args.api_token.map(Arc::from),
+        };
+        tokio::spawn(async move {
+            if let Err(e) = api::serve(api_addr, api_state).await {
+                warn!("Management API exited: {e}");
+            }
+        });
+    }
+
+    let mut tracker = Tracker::new(
+        socket,
+        static_peers,
+        args.interval,
+        args.secret_rotation_interval,
+        args.connection_id_validity_window,
+        policy,
+        args.peer_expiry_multiplier,
+        swarms,
+        stats,
+    );
     tracker.start().await?;
     Ok(())
 }